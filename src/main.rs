@@ -1,47 +1,599 @@
 #![windows_subsystem = "windows"]
 
+use clap::{Parser, ValueEnum};
 use eframe::egui;
+use log::LevelFilter;
+use mio::{Events, Poll, Token, Waker};
 use ping::ping;
+use rumqttc::{Client, MqttOptions, QoS};
+use serde::Serialize;
+use simplelog::{Config, WriteLogger};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
 use std::net::ToSocketAddrs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use chrono::Local;
 
 
+// Command-line options controlling the monitoring behaviour. These provide the
+// defaults the GUI starts from and let the tool be pointed at arbitrary hosts
+// and tuned without recompiling.
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about = "Internet Stability Monitor")]
+struct Opt {
+    /// Host to ping (repeat the flag to monitor several targets)
+    #[arg(long = "target", default_values_t = [String::from("google.com")])]
+    targets: Vec<String>,
+
+    /// Interval between probes, in milliseconds
+    #[arg(long, default_value_t = 1000)]
+    interval_ms: u64,
+
+    /// Size of the ping payload, in bytes
+    #[arg(long, default_value_t = 32)]
+    payload_bytes: u16,
+
+    /// Response time above which a spike log file is created, in milliseconds
+    #[arg(long, default_value_t = 175.0)]
+    log_threshold_ms: f64,
+
+    /// Minimum time between auto-generated spike logs, in seconds
+    #[arg(long, default_value_t = 60)]
+    log_cooldown_secs: u64,
+
+    /// MQTT broker host for telemetry publishing
+    #[arg(long, default_value = "localhost")]
+    mqtt_host: String,
+
+    /// MQTT broker port for telemetry publishing
+    #[arg(long, default_value_t = 1883)]
+    mqtt_port: u16,
+
+    /// MQTT topic prefix telemetry is published under
+    #[arg(long, default_value = "ism/telemetry")]
+    mqtt_topic: String,
+
+    /// Publish telemetry to the MQTT broker from startup
+    #[arg(long)]
+    mqtt: bool,
+
+    /// Write a verbose operational log to this path
+    #[arg(long)]
+    log_to: Option<String>,
+
+    /// Format used when exporting captured data
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+}
+
+// Export format for logged data. Text is the original human-readable layout;
+// CSV and JSON are machine-parseable so downstream tools can consume the
+// captured series.
+#[derive(ValueEnum, Clone, Copy, PartialEq, Debug)]
+enum LogFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+impl LogFormat {
+    // File extension used for exports in this format.
+    fn extension(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "txt",
+            LogFormat::Csv => "csv",
+            LogFormat::Json => "jsonl",
+        }
+    }
+
+    // Header line written once at the top of a file, if the format needs one.
+    fn header(&self) -> Option<&'static str> {
+        match self {
+            LogFormat::Csv => {
+                Some("elapsed_s,response_ms,connected,target,cpu_percent,mem_percent,net_rx_bytes,net_tx_bytes")
+            }
+            LogFormat::Text | LogFormat::Json => None,
+        }
+    }
+}
+
+// One exported data point, serialized for the JSON format. CSV rows carry the
+// same columns but are formatted directly in `append_samples`.
+#[derive(Serialize)]
+struct LogRecord<'a> {
+    elapsed_s: f64,
+    response_ms: f64,
+    connected: bool,
+    target: &'a str,
+    cpu_percent: f64,
+    mem_percent: f64,
+    net_rx_bytes: u64,
+    net_tx_bytes: u64,
+}
+
+// Append one target's samples to `out` in the selected format. For the text
+// format a per-section summary header precedes the rows; CSV and JSON emit one
+// record per sample with no per-target header.
+fn append_samples(
+    out: &mut String,
+    format: LogFormat,
+    target: &str,
+    samples: &[Sample],
+    average_response_time: f64,
+    longest_response_time: f64,
+    total_data_sent: u64,
+) {
+    match format {
+        LogFormat::Text => {
+            if samples.is_empty() {
+                out.push_str(&format!("\nPing Target: {}\nNo data to log.\n", target));
+                return;
+            }
+            out.push_str(&format!(
+                "\nPing Target: {}\nAverage Response Time: {:.0} ms\nLongest Response Time: {:.0} ms\nTotal Data Sent: {} bytes\n\n",
+                target, average_response_time, longest_response_time, total_data_sent
+            ));
+            for sample in samples {
+                out.push_str(&format!(
+                    "{:.0} s, {:.0} ms, cpu {:.0}%, mem {:.0}%, net {}/{} B\n",
+                    sample.elapsed.round(),
+                    sample.response_ms,
+                    sample.metrics.cpu_percent,
+                    sample.metrics.mem_percent,
+                    sample.metrics.net_rx_bytes,
+                    sample.metrics.net_tx_bytes
+                ));
+            }
+        }
+        LogFormat::Csv => {
+            for sample in samples {
+                out.push_str(&format!(
+                    "{:.0},{:.0},{},{},{:.0},{:.0},{},{}\n",
+                    sample.elapsed.round(),
+                    sample.response_ms,
+                    sample.response_ms > 0.0,
+                    target,
+                    sample.metrics.cpu_percent,
+                    sample.metrics.mem_percent,
+                    sample.metrics.net_rx_bytes,
+                    sample.metrics.net_tx_bytes
+                ));
+            }
+        }
+        LogFormat::Json => {
+            for sample in samples {
+                let record = LogRecord {
+                    elapsed_s: sample.elapsed.round(),
+                    response_ms: sample.response_ms,
+                    connected: sample.response_ms > 0.0,
+                    target,
+                    cpu_percent: sample.metrics.cpu_percent,
+                    mem_percent: sample.metrics.mem_percent,
+                    net_rx_bytes: sample.metrics.net_rx_bytes,
+                    net_tx_bytes: sample.metrics.net_tx_bytes,
+                };
+                if let Ok(line) = serde_json::to_string(&record) {
+                    out.push_str(&line);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+}
+
+// A single completed probe, published to the telemetry broker as JSON.
+#[derive(Serialize, Clone)]
+struct TelemetrySample {
+    timestamp: String, // Wall-clock time the probe completed
+    target: String, // Host that was pinged
+    response_time_ms: f64, // Measured round-trip time
+    connected: bool, // Whether the probe succeeded
+    total_bytes: u64, // Cumulative bytes sent to this target
+}
+
+// A periodic per-target roll-up, published alongside the raw samples.
+#[derive(Serialize)]
+struct TelemetrySummary {
+    timestamp: String, // Wall-clock time the summary was emitted
+    target: String, // Host the summary describes
+    average_response_ms: f64, // Mean response time over all samples seen
+    longest_response_ms: f64, // Worst response time seen
+    samples: u64, // Number of samples folded into this summary
+}
+
+// Handle onto the telemetry subsystem. A background thread owns the MQTT
+// connection (reconnecting on failure) and receives samples over a channel;
+// the monitor holds this handle and submits each probe as the UI thread
+// records it.
+#[derive(Clone)]
+struct Telemetry {
+    sender: Sender<TelemetrySample>, // Channel into the connection-owning thread
+    enabled: Arc<Mutex<bool>>, // Publish toggle, surfaced in the UI
+}
+
+impl Telemetry {
+    // Spawn the connection-owning thread and return a handle for the monitor.
+    fn spawn(opt: &Opt) -> Self {
+        let (sender, receiver) = mpsc::channel::<TelemetrySample>();
+        let enabled = Arc::new(Mutex::new(opt.mqtt));
+        let host = opt.mqtt_host.clone();
+        let port = opt.mqtt_port;
+        let topic = opt.mqtt_topic.clone();
+
+        thread::spawn(move || {
+            let mut mqtt_options = MqttOptions::new("ism-monitor", host, port);
+            mqtt_options.set_keep_alive(Duration::from_secs(5));
+            let (client, mut connection) = Client::new(mqtt_options, 10);
+
+            // Drive the event loop on its own thread so the connection
+            // reconnects on failure without stalling publication.
+            thread::spawn(move || {
+                for _ in connection.iter() {}
+            });
+
+            // Running per-target tallies feeding the periodic summaries.
+            let mut tallies: HashMap<String, (f64, f64, u64)> = HashMap::new();
+
+            for sample in receiver.iter() {
+                if let Ok(payload) = serde_json::to_string(&sample) {
+                    let _ = client.publish(format!("{}/samples", topic), QoS::AtLeastOnce, false, payload);
+                }
+
+                let entry = tallies.entry(sample.target.clone()).or_insert((0.0, 0.0, 0));
+                entry.0 += sample.response_time_ms;
+                if sample.response_time_ms > entry.1 {
+                    entry.1 = sample.response_time_ms;
+                }
+                entry.2 += 1;
+
+                // Emit a summary every ten samples per target.
+                if entry.2 % 10 == 0 {
+                    let summary = TelemetrySummary {
+                        timestamp: sample.timestamp.clone(),
+                        target: sample.target.clone(),
+                        average_response_ms: entry.0 / entry.2 as f64,
+                        longest_response_ms: entry.1,
+                        samples: entry.2,
+                    };
+                    if let Ok(payload) = serde_json::to_string(&summary) {
+                        let _ = client.publish(format!("{}/summary", topic), QoS::AtLeastOnce, false, payload);
+                    }
+                }
+            }
+        });
+
+        Self { sender, enabled }
+    }
+
+    // Submit a completed probe, dropping it silently if telemetry is disabled.
+    fn publish(&self, sample: TelemetrySample) {
+        if *self.enabled.lock().unwrap() {
+            let _ = self.sender.send(sample);
+        }
+    }
+}
+
+// Snapshot of local system resource usage sampled at probe time, so a latency
+// spike can be told apart from the machine simply being saturated.
+#[derive(Clone, Copy, Default, Serialize)]
+struct SystemMetrics {
+    cpu_percent: f64, // CPU busy time since the previous sample
+    mem_percent: f64, // Used memory as a percentage of total
+    net_rx_bytes: u64, // Cumulative bytes received across all interfaces
+    net_tx_bytes: u64, // Cumulative bytes sent across all interfaces
+}
+
+// One probe result together with the system metrics sampled alongside it.
+#[derive(Clone, Serialize)]
+struct Sample {
+    elapsed: f64, // Active elapsed time when the probe completed, in seconds
+    response_ms: f64, // Measured round-trip time, in milliseconds
+    metrics: SystemMetrics, // Local resource usage at the time of the probe
+}
+
+// Retained state for the CPU delta between successive samples.
+#[derive(Default)]
+struct MetricsCollectorState {
+    prev_cpu_total: u64,
+    prev_cpu_idle: u64,
+}
+
+// Reads procfs on Linux to produce `SystemMetrics`. CPU usage is a delta
+// between successive samples, so the previous totals are kept behind a mutex
+// in the collector, which the prober's worker thread owns.
+#[derive(Clone)]
+struct MetricsCollector {
+    state: Arc<Mutex<MetricsCollectorState>>,
+}
+
+impl MetricsCollector {
+    fn new() -> Self {
+        Self { state: Arc::new(Mutex::new(MetricsCollectorState::default())) }
+    }
+
+    // Sample current resource usage. Returns zeros where a read (or the
+    // platform) is unavailable.
+    fn sample(&self) -> SystemMetrics {
+        let mut metrics = SystemMetrics::default();
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some((total, idle)) = read_cpu_times() {
+                let mut state = self.state.lock().unwrap();
+                let total_delta = total.saturating_sub(state.prev_cpu_total);
+                let idle_delta = idle.saturating_sub(state.prev_cpu_idle);
+                if total_delta > 0 {
+                    metrics.cpu_percent = 100.0 * (total_delta - idle_delta) as f64 / total_delta as f64;
+                }
+                state.prev_cpu_total = total;
+                state.prev_cpu_idle = idle;
+            }
+
+            if let Some((total_kb, available_kb)) = read_mem_info() {
+                if total_kb > 0 {
+                    metrics.mem_percent = 100.0 * (total_kb - available_kb) as f64 / total_kb as f64;
+                }
+            }
+
+            if let Some((rx, tx)) = read_net_dev() {
+                metrics.net_rx_bytes = rx;
+                metrics.net_tx_bytes = tx;
+            }
+        }
+
+        metrics
+    }
+}
+
+// Total and idle (idle + iowait) jiffies from the aggregate `cpu` line.
+#[cfg(target_os = "linux")]
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    let line = stat.lines().next()?;
+    let mut fields = line.split_whitespace();
+    if fields.next()? != "cpu" {
+        return None;
+    }
+    let values: Vec<u64> = fields.filter_map(|v| v.parse().ok()).collect();
+    let total: u64 = values.iter().sum();
+    let idle = values.get(3).copied().unwrap_or(0) + values.get(4).copied().unwrap_or(0);
+    Some((total, idle))
+}
+
+// Total and available memory in kibibytes from /proc/meminfo.
+#[cfg(target_os = "linux")]
+fn read_mem_info() -> Option<(u64, u64)> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let mut total = None;
+    let mut available = None;
+    for line in meminfo.lines() {
+        if let Some(rest) = line.strip_prefix("MemTotal:") {
+            total = rest.split_whitespace().next().and_then(|v| v.parse().ok());
+        } else if let Some(rest) = line.strip_prefix("MemAvailable:") {
+            available = rest.split_whitespace().next().and_then(|v| v.parse().ok());
+        }
+    }
+    Some((total?, available?))
+}
+
+// Receive/transmit byte totals summed across every non-loopback interface.
+#[cfg(target_os = "linux")]
+fn read_net_dev() -> Option<(u64, u64)> {
+    let net = std::fs::read_to_string("/proc/net/dev").ok()?;
+    let mut rx_total = 0u64;
+    let mut tx_total = 0u64;
+    for line in net.lines().skip(2) {
+        let (iface, rest) = match line.split_once(':') {
+            Some(parts) => parts,
+            None => continue,
+        };
+        if iface.trim() == "lo" {
+            continue; // Ignore loopback traffic
+        }
+        let fields: Vec<u64> = rest.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+        // Receive bytes is column 0, transmit bytes is column 8.
+        rx_total += fields.first().copied().unwrap_or(0);
+        tx_total += fields.get(8).copied().unwrap_or(0);
+    }
+    Some((rx_total, tx_total))
+}
+
+// Token used to wake the prober's poll loop when monitoring is toggled.
+const WAKE: Token = Token(0);
+
+// A completed probe handed back to the UI thread over a channel.
+struct ProbeResult {
+    target_index: usize, // Index into `InternetMonitor::targets`
+    response_ms: f64, // Round-trip time, or 0.0 when the probe failed
+    connected: bool, // Whether the probe succeeded
+    metrics: SystemMetrics, // Local resource usage sampled with the probe
+}
+
+// Issue a single synchronous probe against `target`, returning the round-trip
+// time in milliseconds, or 0.0 if resolution or the ping itself failed.
+fn probe_once(target: &str, payload_bytes: u16) -> f64 {
+    let target_ip = match (target, 80).to_socket_addrs() {
+        Ok(mut addrs) => addrs.next().map(|addr| addr.ip()),
+        Err(_e) => None,
+    };
+
+    let target_ip = match target_ip {
+        Some(ip) => ip,
+        None => return 0.0,
+    };
+
+    let payload = vec![0u8; payload_bytes as usize];
+    let start_time = Instant::now();
+    match ping(target_ip, Some(Duration::from_secs(4)), None, None, None, Some(&payload)) {
+        Ok(_) => start_time.elapsed().as_millis() as f64,
+        Err(_) => 0.0,
+    }
+}
+
+// A single persistent background worker that drives a `mio::Poll` event loop,
+// issuing probes for every target once per interval and pushing results back
+// over a channel the UI drains. The loop itself is one long-lived thread, and
+// results are folded into shared state on the UI thread only. The pings are
+// still synchronous: each round runs them on short-lived scoped threads, one
+// per target, joined before the round ends.
+struct Prober {
+    results: Receiver<ProbeResult>, // Completed probes for the UI to drain
+    running: Arc<AtomicBool>, // Whether the loop should currently be probing
+    waker: Waker, // Wakes the loop when `running` changes
+}
+
+impl Prober {
+    // Spawn the worker and return the handle the UI holds.
+    fn spawn(targets: Vec<String>, payload_bytes: u16, interval: Duration) -> Self {
+        let (sender, results) = mpsc::channel::<ProbeResult>();
+        let running = Arc::new(AtomicBool::new(false));
+        let poll = Poll::new().expect("failed to create poll");
+        let waker = Waker::new(poll.registry(), WAKE).expect("failed to create waker");
+
+        let running_worker = Arc::clone(&running);
+        thread::spawn(move || {
+            let collector = MetricsCollector::new();
+            let mut poll = poll;
+            let mut events = Events::with_capacity(8);
+
+            loop {
+                // Wait for the next interval while running, or block until woken.
+                let timeout = if running_worker.load(Ordering::Relaxed) {
+                    Some(interval)
+                } else {
+                    None
+                };
+                if poll.poll(&mut events, timeout).is_err() {
+                    continue;
+                }
+
+                // A non-empty event set means we were woken by a state change;
+                // loop back and re-evaluate the running flag and timeout.
+                if !events.is_empty() {
+                    continue;
+                }
+
+                // Timed out: issue one probe round for every target.
+                if !running_worker.load(Ordering::Relaxed) {
+                    continue;
+                }
+                // Sample system metrics once for the whole round. The CPU
+                // figure is a delta between consecutive samples, so taking it
+                // per target (microseconds apart) would leave every target but
+                // the first reporting ~0%. One round-level sample is shared.
+                let metrics = collector.sample();
+
+                // Probe every target concurrently within the round so a single
+                // unreachable host cannot stall the others by its full ping
+                // timeout. The probe threads are scoped to the round and joined
+                // before the next interval, so no handles leak across rounds.
+                let responses: Vec<f64> = thread::scope(|scope| {
+                    let handles: Vec<_> = targets
+                        .iter()
+                        .map(|target| scope.spawn(|| probe_once(target, payload_bytes)))
+                        .collect();
+                    handles.into_iter().map(|h| h.join().unwrap_or(0.0)).collect()
+                });
+
+                for (target_index, response_ms) in responses.into_iter().enumerate() {
+                    let result = ProbeResult {
+                        target_index,
+                        response_ms,
+                        connected: response_ms > 0.0,
+                        metrics,
+                    };
+                    if sender.send(result).is_err() {
+                        return; // UI gone; shut the worker down.
+                    }
+                }
+            }
+        });
+
+        Self { results, running, waker }
+    }
+
+    // Start or stop probing, waking the loop so it picks up the change at once.
+    fn set_running(&self, running: bool) {
+        self.running.store(running, Ordering::Relaxed);
+        let _ = self.waker.wake();
+    }
+}
+
+// Per-target monitoring state. Each monitored host owns its own stats and
+// status so several endpoints can be compared side by side to localize where
+// instability originates.
+struct TargetState {
+    target: String, // Host being pinged
+    status: Arc<Mutex<String>>, // Current status message for this target
+    response_times: Arc<Mutex<Vec<Sample>>>, // Probe samples with system metrics
+    longest_response_time: Arc<Mutex<f64>>, // Longest response time recorded
+    total_data_sent: Arc<Mutex<u64>>, // Total data sent in bytes
+    last_log_file_name: Arc<Mutex<Option<String>>>, // Track the last log file name
+    last_log_time: Arc<Mutex<Option<Instant>>>, // Time of the last auto-generated log
+}
+
+impl TargetState {
+    fn new(target: String) -> Self {
+        Self {
+            target,
+            status: Arc::new(Mutex::new("Not checked yet".to_string())),
+            response_times: Arc::new(Mutex::new(Vec::new())),
+            longest_response_time: Arc::new(Mutex::new(0.0)),
+            total_data_sent: Arc::new(Mutex::new(0)),
+            last_log_file_name: Arc::new(Mutex::new(None)),
+            last_log_time: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
 // Struct to hold the state of the Internet Monitor application
 struct InternetMonitor {
+    opt: Opt, // Runtime configuration parsed from the command line
     is_monitoring: bool, // Flag indicating if monitoring is active
-    start_time: Option<Instant>, // The time when monitoring started
-    last_check: Option<Instant>, // The time of the last check
+    cumulative_time: Duration, // Active monitoring time accumulated across pauses
+    last_resume: Option<Instant>, // When the current active interval began, if running
     last_frame_time: Instant, // Time of the last frame for FPS control
-    status: Arc<Mutex<String>>, // Current status message
-    response_times: Arc<Mutex<Vec<(f64, f64)>>>, // List of response times (elapsed time, response time)
+    targets: Vec<TargetState>, // Per-target monitoring state
     log_status: Arc<Mutex<Option<String>>>, // Status message for logging
-    total_data_sent: Arc<Mutex<u64>>, // Total data sent in bytes
-    longest_response_time: Arc<Mutex<f64>>, // Longest response time recorded
-    last_log_file_name: Arc<Mutex<Option<String>>>, // Track the last log file name
-    last_log_time: Arc<Mutex<Option<Instant>>>,
+    log_format: LogFormat, // Selected export format for captured data
+    telemetry: Telemetry, // Background MQTT telemetry publisher
+    prober: Prober, // Persistent probe worker feeding results over a channel
 }
 
-impl Default for InternetMonitor {
-    fn default() -> Self {
+impl InternetMonitor {
+    // Build a monitor starting from the given command-line options.
+    fn new(opt: Opt) -> Self {
+        let targets = opt.targets.iter().cloned().map(TargetState::new).collect();
+        let log_format = opt.log_format;
+        let telemetry = Telemetry::spawn(&opt);
+        let prober = Prober::spawn(
+            opt.targets.clone(),
+            opt.payload_bytes,
+            Duration::from_millis(opt.interval_ms),
+        );
         Self {
+            opt,
             is_monitoring: false,
-            start_time: None,
-            last_check: None,
+            cumulative_time: Duration::ZERO,
+            last_resume: None,
             last_frame_time: Instant::now(),
-            status: Arc::new(Mutex::new("Not checked yet".to_string())),
-            response_times: Arc::new(Mutex::new(Vec::new())),
+            targets,
             log_status: Arc::new(Mutex::new(None)),
-            total_data_sent: Arc::new(Mutex::new(0)),
-            longest_response_time: Arc::new(Mutex::new(0.0)),
-            last_log_file_name: Arc::new(Mutex::new(None)),
-            last_log_time: Arc::new(Mutex::new(None)),
+            log_format,
+            telemetry,
+            prober,
         }
     }
+
+    // Active monitoring time, excluding any intervals spent paused or stopped.
+    fn elapsed(&self) -> Duration {
+        self.cumulative_time + self.last_resume.map(|t| t.elapsed()).unwrap_or_default()
+    }
 }
 
 impl eframe::App for InternetMonitor {
@@ -67,13 +619,40 @@ impl eframe::App for InternetMonitor {
                 // Button to start/stop monitoring
                 if ui.button(if self.is_monitoring { "Stop Monitoring" } else { "Start Monitoring" }).clicked() {
                     self.is_monitoring = !self.is_monitoring;
-                    let mut status = self.status.lock().unwrap();
                     if self.is_monitoring {
-                        *status = format!("Monitoring {}...", "google.com");
-                        self.start_time = Some(Instant::now());
-                        self.last_check = Some(Instant::now());
+                        log::info!("Started monitoring {} target(s)", self.targets.len());
+                        for target in &self.targets {
+                            *target.status.lock().unwrap() = format!("Monitoring {}...", target.target);
+                        }
+                        self.cumulative_time = Duration::ZERO;
+                        self.last_resume = Some(Instant::now());
+                        self.prober.set_running(true);
                     } else {
-                        *status = "Not monitoring".to_string();
+                        log::info!("Stopped monitoring");
+                        // Fold the final active interval into the total before stopping.
+                        if let Some(resume) = self.last_resume.take() {
+                            self.cumulative_time += resume.elapsed();
+                        }
+                        self.prober.set_running(false);
+                        for target in &self.targets {
+                            *target.status.lock().unwrap() = "Not monitoring".to_string();
+                        }
+                    }
+                }
+
+                // Button to pause/resume without losing accumulated data
+                if self.is_monitoring {
+                    let paused = self.last_resume.is_none();
+                    if ui.button(if paused { "Resume" } else { "Pause" }).clicked() {
+                        if let Some(resume) = self.last_resume.take() {
+                            // Pausing: bank the elapsed active time.
+                            self.cumulative_time += resume.elapsed();
+                            self.prober.set_running(false);
+                        } else {
+                            // Resuming: start a fresh active interval.
+                            self.last_resume = Some(Instant::now());
+                            self.prober.set_running(true);
+                        }
                     }
                 }
 
@@ -81,14 +660,17 @@ impl eframe::App for InternetMonitor {
                 if ui.button("Clear Data").clicked() {
                     // Stop monitoring
                     self.is_monitoring = false;
-                    let mut status = self.status.lock().unwrap();
-                    *status = "Not monitoring".to_string();
-                    
-                    // Clear data
-                    let mut data = self.response_times.lock().unwrap();
-                    data.clear();
-                    let mut total_data_sent = self.total_data_sent.lock().unwrap();
-                    *total_data_sent = 0; // Reset total data sent to 0
+                    self.cumulative_time = Duration::ZERO;
+                    self.last_resume = None;
+                    self.prober.set_running(false);
+                    for target in &self.targets {
+                        *target.status.lock().unwrap() = "Not monitoring".to_string();
+
+                        // Clear data
+                        target.response_times.lock().unwrap().clear();
+                        *target.total_data_sent.lock().unwrap() = 0; // Reset total data sent to 0
+                        *target.longest_response_time.lock().unwrap() = 0.0;
+                    }
 
                     // Update log status message to indicate data has been cleared
                     let mut log_status = self.log_status.lock().unwrap();
@@ -115,48 +697,79 @@ impl eframe::App for InternetMonitor {
                 }
             });
 
-            // Display the current status with conditional color
-            let status = self.status.lock().unwrap();
-            let text_color = if self.is_monitoring {
-                egui::Color32::from_rgb(144, 238, 144) // Light green color
-            } else {
-                egui::Color32::WHITE // Default color
-            };
-            ui.label(egui::RichText::new(format!("Status: {}", *status)).color(text_color));
-
-            // Display elapsed time and response time on separate lines
-            let data = self.response_times.lock().unwrap();
-            if let Some((elapsed_time, response_time)) = data.last() {
-                ui.label(format!("Elapsed Time: {:.0} s", elapsed_time));
-                ui.label(format!("Response Time: {:.0} ms", response_time));
-            } else {
-                ui.label("No data available.");
+            // Toggle for publishing live metrics to the MQTT broker
+            {
+                let mut enabled = self.telemetry.enabled.lock().unwrap();
+                ui.checkbox(&mut *enabled, format!("Publish telemetry to {}:{}", self.opt.mqtt_host, self.opt.mqtt_port));
             }
 
-            // Calculate and display average response time
-            if !data.is_empty() {
-                let average_response_time = data.iter().map(|(_, response_time)| response_time).sum::<f64>() / data.len() as f64;
-                ui.label(format!("Average Response Time: {:.0} ms", average_response_time));
+            // Dropdown selecting the export format for logged data
+            egui::ComboBox::from_label("Log Format")
+                .selected_text(format!("{:?}", self.log_format))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.log_format, LogFormat::Text, "Text");
+                    ui.selectable_value(&mut self.log_format, LogFormat::Csv, "CSV");
+                    ui.selectable_value(&mut self.log_format, LogFormat::Json, "JSON");
+                });
 
-                // Display longest response time
-                if let Some(max_response_time) = data.iter().map(|(_, response_time)| response_time).max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)) {
-                    ui.label(format!("Longest Response Time: {:.0} ms", max_response_time));
-                }
+            // Display active elapsed time once for the whole session
+            if self.is_monitoring {
+                ui.label(format!("Elapsed Time: {:.0} s", self.elapsed().as_secs_f64()));
             }
 
-            // Display total data sent
-            let total_data_sent = self.total_data_sent.lock().unwrap();
-            ui.label(format!("Total Data Sent: {} bytes", total_data_sent));
+            // Render one row per target with its latency, average and state
+            for target in &self.targets {
+                ui.separator();
+
+                let status = target.status.lock().unwrap();
+                let connected = status.starts_with("Connected");
+                let text_color = if connected {
+                    egui::Color32::from_rgb(144, 238, 144) // Light green color
+                } else if self.is_monitoring {
+                    egui::Color32::from_rgb(238, 144, 144) // Light red color
+                } else {
+                    egui::Color32::WHITE // Default color
+                };
+                ui.label(egui::RichText::new(format!("{}: {}", target.target, *status)).color(text_color));
+
+                let data = target.response_times.lock().unwrap();
+                if let Some(sample) = data.last() {
+                    ui.label(format!("    Response Time: {:.0} ms", sample.response_ms));
+                    ui.label(format!(
+                        "    Local Load: CPU {:.0}%, Mem {:.0}%, Net {}/{} B rx/tx",
+                        sample.metrics.cpu_percent,
+                        sample.metrics.mem_percent,
+                        sample.metrics.net_rx_bytes,
+                        sample.metrics.net_tx_bytes
+                    ));
+                } else {
+                    ui.label("    No data available.");
+                }
 
-            // Check if monitoring is active
-            if self.is_monitoring {
-                if let Some(last_check) = self.last_check {
-                    // Check every second
-                    if last_check.elapsed() >= Duration::from_secs(1) {
-                        self.check_connection();
-                        self.last_check = Some(Instant::now());
+                // Calculate and display average response time
+                if !data.is_empty() {
+                    let average_response_time = data.iter().map(|s| s.response_ms).sum::<f64>() / data.len() as f64;
+                    ui.label(format!("    Average Response Time: {:.0} ms", average_response_time));
+
+                    // Display longest response time
+                    if let Some(max_response_time) = data.iter().map(|s| s.response_ms).max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)) {
+                        ui.label(format!("    Longest Response Time: {:.0} ms", max_response_time));
                     }
                 }
+
+                // Display total data sent
+                let total_data_sent = target.total_data_sent.lock().unwrap();
+                ui.label(format!("    Total Data Sent: {} bytes", total_data_sent));
+            }
+
+            // Drain any probes the background worker has completed since the
+            // last frame and fold them into the per-target state.
+            if self.is_monitoring && self.last_resume.is_some() {
+                let elapsed_since_start = self.elapsed().as_secs_f64();
+                let results: Vec<ProbeResult> = self.prober.results.try_iter().collect();
+                for result in results {
+                    self.record_probe(result, elapsed_since_start);
+                }
             }
         });
 
@@ -166,200 +779,167 @@ impl eframe::App for InternetMonitor {
 }
 
 impl InternetMonitor {
-    // Function to check the internet connection
-    fn check_connection(&self) {
-        let status_clone = Arc::clone(&self.status);
-        let response_times_clone = Arc::clone(&self.response_times);
-        let start_time_clone = self.start_time.clone();
-        let total_data_sent_clone = Arc::clone(&self.total_data_sent);
-        let longest_response_time_clone = Arc::clone(&self.longest_response_time);
-        let last_log_file_name_clone = Arc::clone(&self.last_log_file_name);
-        let last_log_time_clone = Arc::clone(&self.last_log_time);
-    
-        thread::spawn(move || {
-            let target = "google.com";
-            let target_ip = match (target, 80).to_socket_addrs() {
-                Ok(mut addrs) => addrs.next().map(|addr| addr.ip()),
-                Err(_e) => None,
-            };
-    
-            let target_ip = match target_ip {
-                Some(ip) => ip,
-                None => return,
-            };
-    
-            let start_time = Instant::now();
-    
-            // Perform the ping
-            let result = ping(target_ip, None, Some(32), None, None, None);
-    
-            let response_time_ms = match result {
-                Ok(_) => start_time.elapsed().as_millis() as f64,
-                Err(_) => 0.0,
-            };
-    
-            let status_message = if response_time_ms > 0.0 {
-                format!("Connected to {}.", target)
-            } else {
-                format!("Disconnected from {}.", target)
-            };
-    
-            let mut status = status_clone.lock().unwrap();
-            *status = status_message;
-    
-            let elapsed_since_start = if let Some(start_time) = start_time_clone {
-                Instant::now().duration_since(start_time).as_secs_f64()
-            } else {
-                0.0
-            };
-    
-            let mut data = response_times_clone.lock().unwrap();
-            data.push((elapsed_since_start, response_time_ms));
-    
-            // Update longest response time
-            let mut longest_response_time = longest_response_time_clone.lock().unwrap();
-            if response_time_ms > *longest_response_time {
-                *longest_response_time = response_time_ms;
-    
-                // Check if we need to create a new log file
-                if response_time_ms > 175.0 {
-                    // Check if it's been at least a minute since the last log
-                    let mut last_log_time = last_log_time_clone.lock().unwrap();
-                    if last_log_time.map_or(true, |t| t.elapsed() >= Duration::from_secs(60)) {
-                        let timestamp = Local::now().format("%Y%m%d%H%M%S").to_string();
-                        let new_log_file_name = format!("log_{}.txt", timestamp);
-    
-                        let mut last_log_file_name = last_log_file_name_clone.lock().unwrap();
-                        *last_log_file_name = Some(new_log_file_name.clone());
-    
-                        // Log data to the new file
-                        let data_clone = Arc::clone(&response_times_clone);
-                        let total_data_sent_clone = Arc::clone(&total_data_sent_clone);
-                        let longest_response_time_clone = Arc::clone(&longest_response_time_clone);
-    
-                        thread::spawn(move || {
-                            let data = data_clone.lock().unwrap();
-                            let average_response_time = if !data.is_empty() {
-                                data.iter().map(|(_, response_time)| response_time).sum::<f64>() / data.len() as f64
-                            } else {
-                                0.0
-                            };
-    
-                            let total_data_sent = *total_data_sent_clone.lock().unwrap();
-                            let longest_response_time = *longest_response_time_clone.lock().unwrap();
-    
+    // Fold a single completed probe, drained from the background worker, into
+    // the owning target's state: status, samples, longest response, spike log,
+    // data counter and telemetry.
+    fn record_probe(&self, result: ProbeResult, elapsed_since_start: f64) {
+        let target_state = match self.targets.get(result.target_index) {
+            Some(target_state) => target_state,
+            None => return,
+        };
+        let response_time_ms = result.response_ms;
+
+        *target_state.status.lock().unwrap() = if result.connected {
+            format!("Connected to {}.", target_state.target)
+        } else {
+            format!("Disconnected from {}.", target_state.target)
+        };
+
+        let mut data = target_state.response_times.lock().unwrap();
+        data.push(Sample {
+            elapsed: elapsed_since_start,
+            response_ms: response_time_ms,
+            metrics: result.metrics,
+        });
+
+        // Update longest response time
+        let mut longest_response_time = target_state.longest_response_time.lock().unwrap();
+        if response_time_ms > *longest_response_time {
+            *longest_response_time = response_time_ms;
+
+            // Check if we need to create a new log file
+            if response_time_ms > self.opt.log_threshold_ms {
+                // Check if it's been at least the cooldown since the last log
+                let log_cooldown = Duration::from_secs(self.opt.log_cooldown_secs);
+                let mut last_log_time = target_state.last_log_time.lock().unwrap();
+                if last_log_time.map_or(true, |t| t.elapsed() >= log_cooldown) {
+                    let timestamp = Local::now().format("%Y%m%d%H%M%S").to_string();
+                    let format = self.log_format;
+                    let new_log_file_name = format!("log_{}.{}", timestamp, format.extension());
+
+                    let mut last_log_file_name = target_state.last_log_file_name.lock().unwrap();
+                    *last_log_file_name = Some(new_log_file_name.clone());
+                    log::info!("Response time {:.0} ms exceeded threshold; writing {}", response_time_ms, new_log_file_name);
+
+                    // Log data to the new file using the selected export format
+                    let data_clone = Arc::clone(&target_state.response_times);
+                    let total_data_sent_clone = Arc::clone(&target_state.total_data_sent);
+                    let longest_response_time_clone = Arc::clone(&target_state.longest_response_time);
+                    let target = target_state.target.clone();
+
+                    thread::spawn(move || {
+                        let data = data_clone.lock().unwrap();
+                        let average_response_time = if !data.is_empty() {
+                            data.iter().map(|s| s.response_ms).sum::<f64>() / data.len() as f64
+                        } else {
+                            0.0
+                        };
+
+                        let total_data_sent = *total_data_sent_clone.lock().unwrap();
+                        let longest_response_time = *longest_response_time_clone.lock().unwrap();
+
+                        let mut log_content = String::new();
+                        if let Some(header) = format.header() {
+                            log_content.push_str(header);
+                            log_content.push('\n');
+                        } else if format == LogFormat::Text {
                             let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    
-                            let log_content = if !data.is_empty() {
-                                let mut log_content = format!(
-                                    "Log Created: {}\nPing Target: {}\nAverage Response Time: {:.0} ms\nLongest Response Time: {:.0} ms\nTotal Data Sent: {} bytes\n\n",
-                                    timestamp,
-                                    target,
-                                    average_response_time,
-                                    longest_response_time,
-                                    total_data_sent
-                                );
-    
-                                for (elapsed_time, response_time) in data.iter() {
-                                    let rounded_elapsed_time = elapsed_time.round();
-                                    log_content.push_str(&format!(
-                                        "{:.0} s, {:.0} ms\n",
-                                        rounded_elapsed_time, response_time
-                                    ));
-                                }
-    
-                                log_content
-                            } else {
-                                format!("Log Created: {}\nNo data to log.", timestamp)
-                            };
-    
-                            let mut file = match File::create(&new_log_file_name) {
-                                Ok(file) => file,
-                                Err(_e) => return,
-                            };
-    
-                            if let Err(_e) = writeln!(file, "{}", log_content) {
-                                return;
-                            }
-                        });
-    
-                        // Update the last log time
-                        *last_log_time = Some(Instant::now());
-                    }
+                            log_content.push_str(&format!("Log Created: {}\n", timestamp));
+                        }
+                        append_samples(
+                            &mut log_content,
+                            format,
+                            &target,
+                            &data,
+                            average_response_time,
+                            longest_response_time,
+                            total_data_sent,
+                        );
+
+                        let mut file = match File::create(&new_log_file_name) {
+                            Ok(file) => file,
+                            Err(_e) => return,
+                        };
+
+                        if let Err(_e) = write!(file, "{}", log_content) {
+                            return;
+                        }
+                    });
+
+                    // Update the last log time
+                    *last_log_time = Some(Instant::now());
                 }
             }
-    
-            let mut total_data_sent = total_data_sent_clone.lock().unwrap();
-            *total_data_sent += 32;
-    
-            // Keep the data size manageable
-            if data.len() > 100 {
-                data.remove(0);
-            }
-    
-            // Avoid busy-waiting
-            thread::sleep(Duration::from_millis(100));
+        }
+
+        let mut total_data_sent = target_state.total_data_sent.lock().unwrap();
+        *total_data_sent += self.opt.payload_bytes as u64;
+
+        // Publish this probe to the telemetry broker if enabled.
+        self.telemetry.publish(TelemetrySample {
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            target: target_state.target.clone(),
+            response_time_ms,
+            connected: result.connected,
+            total_bytes: *total_data_sent,
         });
-    }    
 
-    // Function to log data to a file
+        // Keep the data size manageable
+        if data.len() > 100 {
+            data.remove(0);
+        }
+    }
+
+    // Function to log data to a file in the currently selected format. Each
+    // invocation writes a fresh timestamped file rather than overwriting a
+    // single log.
     fn log_data(&self) {
-        let start_time = self.start_time.clone().unwrap_or_else(Instant::now);
-        let end_time = Instant::now() - Duration::from_secs(100); 
-
-        let data = self.response_times.lock().unwrap();
-        let filtered_data: Vec<(f64, f64)> = data
-            .iter()
-            .filter(|(elapsed_time, _)| {
-                let elapsed_since_start = Duration::from_secs_f64(*elapsed_time);
-                elapsed_since_start > end_time.duration_since(start_time)
-            })
-            .cloned()
-            .collect();
-
-        let average_response_time = if !data.is_empty() {
-            data.iter().map(|(_, response_time)| response_time).sum::<f64>() / data.len() as f64
-        } else {
-            0.0
-        };
+        let format = self.log_format;
+        let file_timestamp = Local::now().format("%Y%m%d%H%M%S").to_string();
+
+        let mut log_content = String::new();
+        if let Some(header) = format.header() {
+            log_content.push_str(header);
+            log_content.push('\n');
+        } else if format == LogFormat::Text {
+            let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            log_content.push_str(&format!("Log Created: {}\n", timestamp));
+        }
+
+        // Append one section per monitored target over the full captured series.
+        for target in &self.targets {
+            let data = target.response_times.lock().unwrap();
 
-        let total_data_sent = *self.total_data_sent.lock().unwrap();
-        let longest_response_time = *self.longest_response_time.lock().unwrap();
+            let average_response_time = if !data.is_empty() {
+                data.iter().map(|s| s.response_ms).sum::<f64>() / data.len() as f64
+            } else {
+                0.0
+            };
 
-        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            let total_data_sent = *target.total_data_sent.lock().unwrap();
+            let longest_response_time = *target.longest_response_time.lock().unwrap();
 
-        let log_content = if !filtered_data.is_empty() {
-            let mut log_content = format!(
-                "Log Created: {}\nPing Target: {}\nAverage Response Time: {:.0} ms\nLongest Response Time: {:.0} ms\nTotal Data Sent: {} bytes\n\n",
-                timestamp,
-                "google.com",
+            append_samples(
+                &mut log_content,
+                format,
+                &target.target,
+                &data,
                 average_response_time,
                 longest_response_time,
-                total_data_sent
+                total_data_sent,
             );
+        }
 
-            for (elapsed_time, response_time) in filtered_data {
-                let rounded_elapsed_time = elapsed_time.round();
-                log_content.push_str(&format!(
-                    "{:.0} s, {:.0} ms\n",
-                    rounded_elapsed_time, response_time
-                ));
-            }
-
-            log_content
-        } else {
-            format!("Log Created: {}\nNo data to log.", timestamp)
-        };
-
-        let file_name = "log.txt";
-        let mut file = match File::create(file_name) {
+        let file_name = format!("log_{}.{}", file_timestamp, format.extension());
+        let mut file = match File::create(&file_name) {
             Ok(file) => file,
             Err(_e) => return,
         };
 
-        if let Err(_e) = writeln!(file, "{}", log_content) {
+        if let Err(_e) = write!(file, "{}", log_content) {
             return;
         }
+        log::info!("Exported captured data to {}", file_name);
 
         let mut log_status = self.log_status.lock().unwrap();
         *log_status = Some("✔".to_string());
@@ -375,10 +955,21 @@ impl InternetMonitor {
 }
 
 fn main() {
+    let opt = Opt::parse();
+
+    // Initialize the verbose operational log if a path was given. WriteLogger
+    // serializes writes internally, so it is safe to log from any thread.
+    if let Some(path) = &opt.log_to {
+        if let Ok(file) = File::create(path) {
+            let _ = WriteLogger::init(LevelFilter::Info, Config::default(), file);
+            log::info!("Logging to {}", path);
+        }
+    }
+
     let options = eframe::NativeOptions::default();
     let _ = eframe::run_native(
         "Internet Stability Monitor",
         options,
-        Box::new(|_cc| Ok(Box::new(InternetMonitor::default()))),
+        Box::new(|_cc| Ok(Box::new(InternetMonitor::new(opt)))),
     );
 }